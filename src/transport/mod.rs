@@ -48,7 +48,12 @@
 //! The following transport implementations are provided:
 //! 
 //! - `UsbTransport`: A USB transport implementation.
+//! - `NetworkTransport`: A USB/IP network transport implementation.
+//! - `UsbDevfsTransport`: A libusb-free Linux `usbdevfs` transport, behind the `usbdevfs` feature.
+mod network;
 mod usb;
+#[cfg(all(target_os = "linux", feature = "usbdevfs"))]
+mod usbdevfs;
 
 const DEFAULT_TRANSPORT_TIMEOUT_MS: u64 = 1000;
 
@@ -61,6 +66,47 @@ pub trait Transport {
     /// Receives raw data from the transport with a specified timeout.
     fn recv_raw(&mut self, timeout: Duration) -> Result<Vec<u8>>;
 
+    /// Issues a USB control transfer, modeled on the standard control-transfer shape:
+    /// `request_type` encodes the direction bit, type and recipient, `request` is the
+    /// request code, and `value`/`index` are the `wValue`/`wIndex` setup fields.
+    ///
+    /// Direction is taken from bit 7 of `request_type` (set = device-to-host/IN, clear =
+    /// host-to-device/OUT), matching the standard USB setup packet layout. On an IN
+    /// transfer `data` is filled with the bytes read back; on an OUT transfer `data` is
+    /// sent as the transfer payload. Returns the number of bytes transferred.
+    ///
+    /// This lets callers read standard USB string/device descriptors (e.g. the
+    /// bootloader's `iSerialNumber` and `bcdDevice`) or issue class/vendor control
+    /// requests such as a DFU-style detach, without being limited to the two bulk
+    /// endpoints. Transports that cannot issue control transfers should return an error.
+    fn control_transfer(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+    ) -> Result<usize>;
+
+    /// Reads exactly `len` bytes from the transport, issuing as many underlying reads as
+    /// needed. Mirrors the "keep issuing IN transfers until the buffer is satisfied"
+    /// pattern used by host-side USB stacks, for protocol code that knows the expected
+    /// response size up front and wants it read back reliably in one call.
+    fn recv_exact(&mut self, len: usize, timeout: Duration) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(len);
+        while buf.len() < len {
+            let chunk = self.recv_raw(timeout)?;
+            anyhow::ensure!(
+                !chunk.is_empty(),
+                "transport returned no data before the expected {} bytes were read",
+                len
+            );
+            buf.extend_from_slice(&chunk);
+        }
+        buf.truncate(len);
+        Ok(buf)
+    }
+
     /// Transfers a command over the transport and returns the response.
     /// Uses the default transport timeout.
     fn transfer(&mut self, cmd: Command) -> Result<Response> {
@@ -75,8 +121,19 @@ pub trait Transport {
         self.send_raw(&req)?;
         sleep(Duration::from_micros(1)); // required for some Linux platform
 
-        let resp = self.recv_raw(wait)?;
+        let mut resp = self.recv_raw(wait)?;
         anyhow::ensure!(req[0] == resp[0], "response command type mismatch");
+
+        // The response header carries a little-endian length at resp[2..4]; pull in any
+        // remaining bytes so large flash-readback responses don't get clipped.
+        if resp.len() >= 4 {
+            let declared_len = 4 + u16::from_le_bytes([resp[2], resp[3]]) as usize;
+            if resp.len() < declared_len {
+                let remaining = declared_len - resp.len();
+                resp.extend_from_slice(&self.recv_exact(remaining, wait)?);
+            }
+        }
+
         log::debug!("<= {} {}", hex::encode(&resp[..4]), hex::encode(&resp[4..]));
         Response::from_raw(&resp)
     }
@@ -0,0 +1,220 @@
+//! USB/IP network transportation.
+//!
+//! This module provides a `NetworkTransport` that implements the `Transport` trait by
+//! speaking the USB/IP protocol to a remote `usbipd` server over TCP, instead of talking
+//! to a locally attached device. This lets `wchisp` run on one machine while the WCH chip
+//! is plugged into another, as long as that other machine exports the device via USB/IP.
+//!
+//! # Examples
+//!
+//! Connecting to a remote device exported as busid `1-1`:
+//!
+//! ```no_run
+//! use wchisp::transport::network::NetworkTransport;
+//!
+//! let transport = NetworkTransport::connect("192.168.1.42:3240", "1-1").unwrap();
+//! ```
+//!
+//! # Notes
+//!
+//! - The default USB/IP server port is `3240`.
+//! - All multi-byte header fields in the USB/IP wire protocol are big-endian.
+//! - This only swaps the byte pipe underneath `Transport::transfer_with_wait`; the
+//!   higher-level command framing is unchanged.
+//!
+//! # References
+//!
+//! - [USB/IP protocol documentation](https://docs.kernel.org/usb/usbip_protocol.html)
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+
+use super::Transport;
+
+const ENDPOINT_OUT: u8 = 0x02;
+const ENDPOINT_IN: u8 = 0x82;
+
+const DEFAULT_USBIP_PORT: u16 = 3240;
+
+const USBIP_VERSION: u16 = 0x0111;
+const OP_REQ_IMPORT: u16 = 0x8003;
+const OP_REP_IMPORT: u16 = 0x0003;
+
+const USBIP_CMD_SUBMIT: u32 = 0x00000001;
+const USBIP_RET_SUBMIT: u32 = 0x00000003;
+
+const USBIP_DIR_OUT: u32 = 0;
+const USBIP_DIR_IN: u32 = 1;
+
+// Mask that clears the bEndpointAddress direction bit (0x80, USB spec); USB/IP's `ep` field
+// wants the bare endpoint number with that bit masked off, since direction is already
+// carried separately.
+const ENDPOINT_NUMBER_MASK: u8 = 0x7f;
+
+// Default read/write timeout used where the `Transport` trait doesn't hand us one
+// (connecting, and `send_raw`, which has no timeout parameter).
+const DEFAULT_NETWORK_TIMEOUT_MS: u64 = 5000;
+
+/// A `Transport` implementation that attaches a remote USB device exported by a
+/// `usbipd` server and exchanges bulk transfers with it over TCP.
+pub struct NetworkTransport {
+    stream: TcpStream,
+    devid: u32,
+    seqnum: u32,
+}
+
+impl NetworkTransport {
+    /// Connects to a `usbipd` server at `addr` (e.g. `"192.168.1.42:3240"`) and imports
+    /// the `4348:55e0` device exported under `busid` (e.g. `"1-1"`).
+    pub fn connect(addr: &str, busid: &str) -> Result<NetworkTransport> {
+        let mut stream = TcpStream::connect(addr).context("Failed to connect to usbipd server")?;
+        stream.set_nodelay(true)?;
+        let default_timeout = Duration::from_millis(DEFAULT_NETWORK_TIMEOUT_MS);
+        stream.set_read_timeout(Some(default_timeout))?;
+        stream.set_write_timeout(Some(default_timeout))?;
+
+        let devid = Self::import_device(&mut stream, busid)?;
+
+        Ok(NetworkTransport {
+            stream,
+            devid,
+            seqnum: 0,
+        })
+    }
+
+    /// Connects to a `usbipd` server running on the default port `3240` on `host`.
+    pub fn connect_default_port(host: &str, busid: &str) -> Result<NetworkTransport> {
+        Self::connect(&format!("{}:{}", host, DEFAULT_USBIP_PORT), busid)
+    }
+
+    // Sends OP_REQ_IMPORT for `busid` and parses OP_REP_IMPORT to recover the devid.
+    fn import_device(stream: &mut TcpStream, busid: &str) -> Result<u32> {
+        let mut req = Vec::with_capacity(8 + 32);
+        req.extend_from_slice(&USBIP_VERSION.to_be_bytes());
+        req.extend_from_slice(&OP_REQ_IMPORT.to_be_bytes());
+        req.extend_from_slice(&0u32.to_be_bytes()); // status
+
+        let mut busid_field = [0u8; 32];
+        let busid_bytes = busid.as_bytes();
+        anyhow::ensure!(busid_bytes.len() < 32, "busid too long: {}", busid);
+        busid_field[..busid_bytes.len()].copy_from_slice(busid_bytes);
+        req.extend_from_slice(&busid_field);
+
+        stream.write_all(&req)?;
+
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header)?;
+        let reply_code = u16::from_be_bytes([header[2], header[3]]);
+        let status = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        anyhow::ensure!(
+            reply_code == OP_REP_IMPORT,
+            "unexpected OP_REP_IMPORT reply code: {:#06x}",
+            reply_code
+        );
+        anyhow::ensure!(status == 0, "usbipd rejected import of {}: status={}", busid, status);
+
+        // usbip_usb_device: path[256] + busid[32] + busnum(4) + devnum(4) + speed(4)
+        // + idVendor(2) + idProduct(2) + bcdDevice(2) + 6 single-byte class fields = 312.
+        let mut device = [0u8; 256 + 32 + 4 + 4 + 4 + 2 + 2 + 2 + 6];
+        stream.read_exact(&mut device)?;
+
+        let busnum = u32::from_be_bytes(device[288..292].try_into().unwrap());
+        let devnum = u32::from_be_bytes(device[292..296].try_into().unwrap());
+        let devid = (busnum << 16) | devnum;
+
+        Ok(devid)
+    }
+
+    fn next_seqnum(&mut self) -> u32 {
+        self.seqnum += 1;
+        self.seqnum
+    }
+
+    // Applies `timeout` to both directions of the underlying stream before a blocking
+    // read/write, so a non-responding usbipd/device can't hang the caller forever.
+    fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        self.stream.set_write_timeout(Some(timeout))?;
+        Ok(())
+    }
+
+    // Builds the 48-byte USBIP_CMD_SUBMIT header for a bulk transfer. `ep` is the endpoint
+    // address (e.g. 0x82); its direction bit is masked off, since direction is carried
+    // separately in `direction`.
+    fn submit_header(&mut self, ep: u8, direction: u32, buffer_len: u32) -> [u8; 48] {
+        let mut header = [0u8; 48];
+        header[0..4].copy_from_slice(&USBIP_CMD_SUBMIT.to_be_bytes());
+        header[4..8].copy_from_slice(&self.next_seqnum().to_be_bytes());
+        header[8..12].copy_from_slice(&self.devid.to_be_bytes());
+        header[12..16].copy_from_slice(&direction.to_be_bytes());
+        header[16..20].copy_from_slice(&((ep & ENDPOINT_NUMBER_MASK) as u32).to_be_bytes());
+        // transfer_flags
+        header[20..24].copy_from_slice(&0u32.to_be_bytes());
+        header[24..28].copy_from_slice(&buffer_len.to_be_bytes());
+        // start_frame, number_of_packets, interval
+        header[28..32].copy_from_slice(&0u32.to_be_bytes());
+        header[32..36].copy_from_slice(&0u32.to_be_bytes());
+        header[36..40].copy_from_slice(&0u32.to_be_bytes());
+        // setup (8 bytes, zeroed for bulk transfers)
+        header
+    }
+}
+
+impl Transport for NetworkTransport {
+    fn control_transfer(
+        &mut self,
+        _request_type: u8,
+        _request: u8,
+        _value: u16,
+        _index: u16,
+        _data: &mut [u8],
+    ) -> Result<usize> {
+        anyhow::bail!("control transfers are not supported over the USB/IP network transport")
+    }
+
+    fn send_raw(&mut self, raw: &[u8]) -> Result<()> {
+        self.set_timeout(Duration::from_millis(DEFAULT_NETWORK_TIMEOUT_MS))?;
+
+        let header = self.submit_header(ENDPOINT_OUT, USBIP_DIR_OUT, raw.len() as u32);
+        self.stream.write_all(&header)?;
+        self.stream.write_all(raw)?;
+
+        let mut reply_header = [0u8; 48];
+        self.stream.read_exact(&mut reply_header)?;
+        let command = u32::from_be_bytes(reply_header[0..4].try_into().unwrap());
+        anyhow::ensure!(
+            command == USBIP_RET_SUBMIT,
+            "unexpected USB/IP reply command: {:#010x}",
+            command
+        );
+        let status = i32::from_be_bytes(reply_header[20..24].try_into().unwrap());
+        anyhow::ensure!(status == 0, "USB/IP OUT transfer failed with status {}", status);
+
+        Ok(())
+    }
+
+    fn recv_raw(&mut self, timeout: Duration) -> Result<Vec<u8>> {
+        self.set_timeout(timeout)?;
+
+        let header = self.submit_header(ENDPOINT_IN, USBIP_DIR_IN, 64);
+        self.stream.write_all(&header)?;
+
+        let mut reply_header = [0u8; 48];
+        self.stream.read_exact(&mut reply_header)?;
+        let command = u32::from_be_bytes(reply_header[0..4].try_into().unwrap());
+        anyhow::ensure!(
+            command == USBIP_RET_SUBMIT,
+            "unexpected USB/IP reply command: {:#010x}",
+            command
+        );
+        let status = i32::from_be_bytes(reply_header[20..24].try_into().unwrap());
+        anyhow::ensure!(status == 0, "USB/IP IN transfer failed with status {}", status);
+        let actual_length = u32::from_be_bytes(reply_header[24..28].try_into().unwrap()) as usize;
+
+        let mut data = vec![0u8; actual_length];
+        self.stream.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
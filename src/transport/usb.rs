@@ -51,7 +51,12 @@
 //!
 //! - The USB device handle is automatically released when the `UsbTransport` object is dropped.
 //! - Communication errors are ignored when releasing the interface.
-//! - The USB device is not reset when the `UsbTransport` object is dropped.
+//! - The USB device is only reset on drop if `reset_on_drop` is set to `true`.
+//! - Stalled bulk endpoints are automatically cleared and the transfer retried up to
+//!   `stall_retries` times.
+//! - `recv_raw` keeps reading IN packets until a short packet (below `wMaxPacketSize`)
+//!   terminates the transfer or the response's declared length is reached, so responses
+//!   longer than one USB packet are not truncated.
 //!
 //! # Errors
 //!
@@ -89,8 +94,46 @@ const ENDPOINT_IN: u8 = 0x82;
 
 const USB_TIMEOUT_MS: u64 = 5000;
 
+// Maximum packet size of the bulk endpoints; a short read below this terminates a transfer.
+const W_MAX_PACKET_SIZE: usize = 64;
+
+// Default number of times a stalled bulk transfer is retried after clearing the halt.
+const DEFAULT_STALL_RETRIES: u32 = 1;
+
+// Vendor control request used to clear the ISP session (modeled on the USBTMC
+// INITIATE_CLEAR sequence: a vendor clear request followed by polling a status endpoint
+// until the device reports the bus is idle).
+const REQUEST_TYPE_VENDOR_OUT: u8 = 0x40;
+const REQUEST_TYPE_VENDOR_IN: u8 = 0xc0;
+const REQUEST_INITIATE_CLEAR: u8 = 0xff;
+const REQUEST_CHECK_CLEAR_STATUS: u8 = 0xfe;
+const STATUS_BUS_IDLE: u8 = 0x00;
+
+// Bounds for polling the clear-status endpoint in `abort_and_reset`, so a device that
+// never reports idle doesn't spin the caller forever.
+const ABORT_POLL_MAX_ATTEMPTS: u32 = 50;
+const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 pub struct UsbTransport {
     device_handle: DeviceHandle<rusb::Context>,
+    /// Number of times to retry a bulk transfer after clearing a stalled endpoint.
+    pub stall_retries: u32,
+    /// Whether to issue a bus reset when the transport is dropped.
+    pub reset_on_drop: bool,
+}
+
+// Signals `wait_for_device` through a channel when a matching device arrives.
+struct ArrivalNotifier {
+    tx: std::sync::mpsc::Sender<()>,
+}
+
+impl rusb::Hotplug<rusb::Context> for ArrivalNotifier {
+    fn device_arrived(&mut self, device: rusb::Device<rusb::Context>) {
+        log::debug!("WCH ISP USB device arrived: {:?}", device);
+        let _ = self.tx.send(());
+    }
+
+    fn device_left(&mut self, _device: rusb::Device<rusb::Context>) {}
 }
 
 impl UsbTransport {
@@ -114,6 +157,14 @@ impl UsbTransport {
             .count();
         Ok(n)
     }
+    // Returns true if `device` matches the WCH ISP vendor/product ID.
+    fn is_wch_isp_device(device: &rusb::Device<Context>) -> bool {
+        device
+            .device_descriptor()
+            .map(|desc| desc.vendor_id() == 0x4348 && desc.product_id() == 0x55e0)
+            .unwrap_or(false)
+    }
+
     // Attempt to open the nth available device, retrieve devices configuration parameters,
     // checks first interface and its first descriptor for the required endpoints, sets
     // the active configuration and claims the interface.
@@ -123,17 +174,125 @@ impl UsbTransport {
         let device = context
             .devices()?
             .iter()
-            .filter(|device| {
-                device
-                    .device_descriptor()
-                    .map(|desc| desc.vendor_id() == 0x4348 && desc.product_id() == 0x55e0)
-                    .unwrap_or(false)
-            })
+            .filter(Self::is_wch_isp_device)
             .nth(nth)
             .ok_or(anyhow::format_err!(
                 "No WCH ISP USB device found(4348:55e0 device not found at index #{})",
                 nth
             ))?;
+
+        Self::open_device(device)
+    }
+
+    // Convenience function to open the first available device
+    pub fn open_any() -> Result<UsbTransport> {
+        Self::open_nth(0)
+    }
+
+    /// Opens the WCH ISP device whose `iSerialNumber` string descriptor matches `serial`.
+    ///
+    /// Useful for re-attaching to the same physical board when several are connected, or
+    /// when a board re-enumerates (and so changes bus/address) during the bootloader-entry
+    /// sequence.
+    pub fn open_by_serial(serial: &str) -> Result<UsbTransport> {
+        let context = Context::new()?;
+
+        let device = context
+            .devices()?
+            .iter()
+            .filter(Self::is_wch_isp_device)
+            .find(|device| {
+                device
+                    .open()
+                    .and_then(|handle| {
+                        let desc = device.device_descriptor()?;
+                        let timeout = Duration::from_millis(USB_TIMEOUT_MS);
+                        let languages = handle.read_languages(timeout)?;
+                        let language = languages.first().ok_or(rusb::Error::NotFound)?;
+                        handle.read_serial_number_string(*language, &desc, timeout)
+                    })
+                    .map(|found| found == serial)
+                    .unwrap_or(false)
+            })
+            .ok_or(anyhow::format_err!(
+                "No WCH ISP USB device found with serial number {:?}",
+                serial
+            ))?;
+
+        Self::open_device(device)
+    }
+
+    /// Opens the WCH ISP device at the given USB `bus` number and device `addr`ess.
+    pub fn open_by_address(bus: u8, addr: u8) -> Result<UsbTransport> {
+        let context = Context::new()?;
+
+        let device = context
+            .devices()?
+            .iter()
+            .filter(Self::is_wch_isp_device)
+            .find(|device| device.bus_number() == bus && device.address() == addr)
+            .ok_or(anyhow::format_err!(
+                "No WCH ISP USB device found at bus {} address {}",
+                bus,
+                addr
+            ))?;
+
+        Self::open_device(device)
+    }
+
+    /// Blocks until a `4348:55e0` device appears on the bus, then opens it.
+    ///
+    /// WCH parts bounce off the bus during the bootloader-entry sequence, so scripted
+    /// flashing needs to wait for the device to come back rather than failing immediately.
+    /// Uses rusb hotplug callbacks where the platform supports them, falling back to
+    /// polling `scan_devices` otherwise.
+    pub fn wait_for_device(timeout: Duration) -> Result<UsbTransport> {
+        if rusb::has_hotplug() {
+            return Self::wait_for_device_hotplug(timeout);
+        }
+        Self::wait_for_device_polling(timeout)
+    }
+
+    // Registers a hotplug callback for device arrival and blocks on `Context::handle_events`
+    // until either a matching device shows up or `timeout` elapses.
+    fn wait_for_device_hotplug(timeout: Duration) -> Result<UsbTransport> {
+        let context = Context::new()?;
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let _registration = rusb::HotplugBuilder::new()
+            .vendor_id(0x4348)
+            .product_id(0x55e0)
+            .enumerate(true)
+            .register(&context, Box::new(ArrivalNotifier { tx }))?;
+
+        let deadline = std::time::Instant::now() + timeout;
+        while std::time::Instant::now() < deadline {
+            context.handle_events(Some(Duration::from_millis(100)))?;
+            if rx.try_recv().is_ok() {
+                return Self::open_nth(0);
+            }
+        }
+
+        anyhow::bail!("Timed out waiting for a WCH ISP USB device to appear")
+    }
+
+    // Falls back to polling `scan_devices` on platforms without rusb hotplug support.
+    fn wait_for_device_polling(timeout: Duration) -> Result<UsbTransport> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            if Self::scan_devices()? > 0 {
+                return Self::open_nth(0);
+            }
+            if std::time::Instant::now() >= deadline {
+                anyhow::bail!("Timed out waiting for a WCH ISP USB device to appear");
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    // Shared device-open path: opens the handle, validates endpoints, sets the active
+    // configuration and claims interface 0.
+    fn open_device(device: rusb::Device<Context>) -> Result<UsbTransport> {
         log::debug!("Found USB Device {:?}", device);
 
         let mut device_handle = match device.open() {
@@ -183,35 +342,162 @@ impl UsbTransport {
 
         device_handle.claim_interface(0)?;
 
-        Ok(UsbTransport { device_handle })
+        Ok(UsbTransport {
+            device_handle,
+            stall_retries: DEFAULT_STALL_RETRIES,
+            reset_on_drop: false,
+        })
     }
 
-    // Convenience function to open the first available device
-    pub fn open_any() -> Result<UsbTransport> {
-        Self::open_nth(0)
+    // Returns true if `err` indicates a stalled/halted endpoint.
+    fn is_stall_error(err: &rusb::Error) -> bool {
+        matches!(err, rusb::Error::Pipe)
+    }
+
+    /// Aborts and resets an in-progress ISP session without dropping and reopening the
+    /// `UsbTransport`, for recovering from a corrupted/interrupted flash.
+    ///
+    /// This mirrors the USBTMC abort sequence: issue a vendor `InitiateClear`-style
+    /// control request, then poll a status request until the device reports the bus is
+    /// idle again.
+    pub fn abort_and_reset(&mut self) -> Result<()> {
+        self.device_handle.write_control(
+            REQUEST_TYPE_VENDOR_OUT,
+            REQUEST_INITIATE_CLEAR,
+            0,
+            0,
+            &[],
+            Duration::from_millis(USB_TIMEOUT_MS),
+        )?;
+
+        let mut status = [0u8; 1];
+        let mut idle = false;
+        for _ in 0..ABORT_POLL_MAX_ATTEMPTS {
+            self.device_handle.read_control(
+                REQUEST_TYPE_VENDOR_IN,
+                REQUEST_CHECK_CLEAR_STATUS,
+                0,
+                0,
+                &mut status,
+                Duration::from_millis(USB_TIMEOUT_MS),
+            )?;
+            if status[0] == STATUS_BUS_IDLE {
+                idle = true;
+                break;
+            }
+            std::thread::sleep(ABORT_POLL_INTERVAL);
+        }
+        anyhow::ensure!(
+            idle,
+            "device did not report bus idle after {} abort status polls",
+            ABORT_POLL_MAX_ATTEMPTS
+        );
+
+        self.clear_halt(ENDPOINT_OUT)?;
+        self.clear_halt(ENDPOINT_IN)?;
+
+        Ok(())
+    }
+
+    fn clear_halt(&mut self, endpoint: u8) -> Result<()> {
+        self.device_handle.clear_halt(endpoint)?;
+        Ok(())
+    }
+
+    // Reads a single `wMaxPacketSize` IN packet, clearing a stalled endpoint and retrying
+    // up to `stall_retries` times.
+    fn read_in_packet(&mut self, timeout: Duration) -> Result<Vec<u8>> {
+        let mut attempts_left = self.stall_retries;
+        loop {
+            let mut buf = [0u8; W_MAX_PACKET_SIZE];
+            match self.device_handle.read_bulk(ENDPOINT_IN, &mut buf, timeout) {
+                Ok(nread) => return Ok(buf[..nread].to_vec()),
+                Err(e) if Self::is_stall_error(&e) && attempts_left > 0 => {
+                    log::warn!("IN endpoint stalled, clearing halt and retrying: {}", e);
+                    self.clear_halt(ENDPOINT_IN)?;
+                    attempts_left -= 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
     }
 }
 
 impl Drop for UsbTransport {
     fn drop(&mut self) {
         // ignore any communication error
+        if self.reset_on_drop {
+            let _ = self.device_handle.reset();
+        }
         let _ = self.device_handle.release_interface(0);
-        // self.device_handle.reset().unwrap();
     }
 }
 
+// Bit 7 of bmRequestType selects the transfer direction: set means device-to-host (IN).
+const REQUEST_TYPE_DIRECTION_IN: u8 = 0x80;
+
 impl Transport for UsbTransport {
+    fn control_transfer(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+    ) -> Result<usize> {
+        let timeout = Duration::from_millis(USB_TIMEOUT_MS);
+        if request_type & REQUEST_TYPE_DIRECTION_IN != 0 {
+            Ok(self
+                .device_handle
+                .read_control(request_type, request, value, index, data, timeout)?)
+        } else {
+            Ok(self
+                .device_handle
+                .write_control(request_type, request, value, index, data, timeout)?)
+        }
+    }
+
     fn send_raw(&mut self, raw: &[u8]) -> Result<()> {
-        self.device_handle
-            .write_bulk(ENDPOINT_OUT, raw, Duration::from_millis(USB_TIMEOUT_MS))?;
-        Ok(())
+        let mut attempts_left = self.stall_retries;
+        loop {
+            match self
+                .device_handle
+                .write_bulk(ENDPOINT_OUT, raw, Duration::from_millis(USB_TIMEOUT_MS))
+            {
+                Ok(_) => return Ok(()),
+                Err(e) if Self::is_stall_error(&e) && attempts_left > 0 => {
+                    log::warn!("OUT endpoint stalled, clearing halt and retrying: {}", e);
+                    self.clear_halt(ENDPOINT_OUT)?;
+                    attempts_left -= 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
     }
 
     fn recv_raw(&mut self, timeout: Duration) -> Result<Vec<u8>> {
-        let mut buf = [0u8; 64];
-        let nread = self
-            .device_handle
-            .read_bulk(ENDPOINT_IN, &mut buf, timeout)?;
-        Ok(buf[..nread].to_vec())
+        let mut buf = Vec::new();
+        loop {
+            let packet = self.read_in_packet(timeout)?;
+            let nread = packet.len();
+            buf.extend_from_slice(&packet);
+
+            // Once the response header is in, stop as soon as its declared length
+            // (resp[2..4], little-endian, plus the 4-byte header) is satisfied, rather
+            // than waiting on a trailing short packet that a full-size response may never
+            // send.
+            let declared_len = (buf.len() >= 4)
+                .then(|| 4 + u16::from_le_bytes([buf[2], buf[3]]) as usize);
+
+            let satisfied = match declared_len {
+                Some(len) => buf.len() >= len,
+                None => false,
+            };
+
+            if satisfied || nread < W_MAX_PACKET_SIZE {
+                break;
+            }
+        }
+        Ok(buf)
     }
 }
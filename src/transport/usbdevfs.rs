@@ -0,0 +1,166 @@
+//! Native Linux `usbdevfs` backend.
+//!
+//! This module provides `UsbDevfsTransport`, a separate transport alongside `UsbTransport`
+//! that talks directly to the Linux kernel via `/dev/bus/usb/BBB/DDD` usbdevfs ioctls,
+//! instead of going through `rusb`/libusb. It is only compiled when the `usbdevfs` cargo
+//! feature is enabled, and only available on Linux.
+//!
+//! `UsbDevfsTransport` itself does not use libusb, which leaves room to add features like
+//! endpoint reset without waiting on upstream `rusb`. Enabling the feature does not
+//! replace `UsbTransport`: it and its `rusb`/libusb dependency are still compiled.
+//!
+//! # Notes
+//!
+//! - Devices are enumerated by scanning sysfs (`/sys/bus/usb/devices`) for the
+//!   `4348:55e0` idVendor/idProduct pair.
+//! - Only interface 0 and endpoints `0x02`/`0x82` are used, matching `UsbTransport`.
+//!
+//! # References
+//!
+//! - [usbdevfs(5) man page](https://man7.org/linux/man-pages/man5/usbdevfs.5.html)
+#![cfg(all(target_os = "linux", feature = "usbdevfs"))]
+
+use std::fs::{self, File};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+
+use super::Transport;
+
+const ENDPOINT_OUT: u8 = 0x02;
+const ENDPOINT_IN: u8 = 0x82;
+
+// Linux ioctl request number encoding: dir(2) | size(14) | type(8) | nr(8), per
+// `include/uapi/asm-generic/ioctl.h`. usbdevfs ioctls are all type 'U'.
+const IOC_READ: libc::c_ulong = 2;
+const IOC_WRITE: libc::c_ulong = 1;
+const IOC_TYPE_USBDEVFS: libc::c_ulong = b'U' as libc::c_ulong;
+
+const fn ioc(dir: libc::c_ulong, nr: libc::c_ulong, size: usize) -> libc::c_ulong {
+    (dir << 30) | ((size as libc::c_ulong) << 16) | (IOC_TYPE_USBDEVFS << 8) | nr
+}
+
+/// A `/dev/bus/usb` bulk transfer descriptor, mirroring the kernel's
+/// `struct usbdevfs_bulktransfer`.
+#[repr(C)]
+struct UsbdevfsBulkTransfer {
+    ep: u32,
+    len: u32,
+    timeout: u32,
+    data: *mut libc::c_void,
+}
+
+// `_IOR('U', 15, unsigned int)`.
+fn usbdevfs_claiminterface() -> libc::c_ulong {
+    ioc(IOC_READ, 15, std::mem::size_of::<libc::c_uint>())
+}
+
+// `_IOWR('U', 2, struct usbdevfs_bulktransfer)`. The struct's size (and therefore this
+// ioctl number) depends on pointer width, so it must be derived from `size_of` rather
+// than a hardcoded magic number.
+fn usbdevfs_bulk() -> libc::c_ulong {
+    ioc(IOC_READ | IOC_WRITE, 2, std::mem::size_of::<UsbdevfsBulkTransfer>())
+}
+
+/// A libusb-free alternative to `UsbTransport` that speaks to usbdevfs directly.
+pub struct UsbDevfsTransport {
+    file: File,
+}
+
+impl UsbDevfsTransport {
+    /// Scans sysfs for a `4348:55e0` device and opens its devfs node.
+    pub fn open_any() -> Result<UsbDevfsTransport> {
+        let path = Self::find_device_node().context("No WCH ISP USB device found via usbdevfs")?;
+        Self::open_path(&path)
+    }
+
+    /// Opens the devfs node at `/dev/bus/usb/BBB/DDD` directly and claims interface 0.
+    pub fn open_path(path: &str) -> Result<UsbDevfsTransport> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open usbdevfs node {}", path))?;
+
+        let interface: libc::c_int = 0;
+        let ret =
+            unsafe { libc::ioctl(file.as_raw_fd(), usbdevfs_claiminterface(), &interface) };
+        anyhow::ensure!(
+            ret == 0,
+            "USBDEVFS_CLAIMINTERFACE failed: {}",
+            std::io::Error::last_os_error()
+        );
+
+        Ok(UsbDevfsTransport { file })
+    }
+
+    // Walks sysfs looking for a device directory whose idVendor/idProduct match 4348:55e0,
+    // returning its /dev/bus/usb/BBB/DDD node path.
+    fn find_device_node() -> Result<String> {
+        for entry in fs::read_dir("/sys/bus/usb/devices")? {
+            let entry = entry?;
+            let dir = entry.path();
+
+            let vendor = fs::read_to_string(dir.join("idVendor")).unwrap_or_default();
+            let product = fs::read_to_string(dir.join("idProduct")).unwrap_or_default();
+            if vendor.trim() != "4348" || product.trim() != "55e0" {
+                continue;
+            }
+
+            let busnum: u32 = fs::read_to_string(dir.join("busnum"))?.trim().parse()?;
+            let devnum: u32 = fs::read_to_string(dir.join("devnum"))?.trim().parse()?;
+            return Ok(format!("/dev/bus/usb/{:03}/{:03}", busnum, devnum));
+        }
+        anyhow::bail!("4348:55e0 device not found in sysfs")
+    }
+
+    fn bulk_transfer(&mut self, ep: u8, data: &mut [u8], timeout: Duration) -> Result<usize> {
+        let mut req = UsbdevfsBulkTransfer {
+            ep: ep as u32,
+            len: data.len() as u32,
+            timeout: timeout.as_millis() as u32,
+            data: data.as_mut_ptr() as *mut libc::c_void,
+        };
+
+        let ret = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                usbdevfs_bulk(),
+                &mut req as *mut UsbdevfsBulkTransfer,
+            )
+        };
+        anyhow::ensure!(
+            ret >= 0,
+            "USBDEVFS_BULK failed: {}",
+            std::io::Error::last_os_error()
+        );
+
+        Ok(ret as usize)
+    }
+}
+
+impl Transport for UsbDevfsTransport {
+    fn control_transfer(
+        &mut self,
+        _request_type: u8,
+        _request: u8,
+        _value: u16,
+        _index: u16,
+        _data: &mut [u8],
+    ) -> Result<usize> {
+        anyhow::bail!("control transfers are not yet implemented for the usbdevfs transport")
+    }
+
+    fn send_raw(&mut self, raw: &[u8]) -> Result<()> {
+        let mut buf = raw.to_vec();
+        self.bulk_transfer(ENDPOINT_OUT, &mut buf, Duration::from_millis(5000))?;
+        Ok(())
+    }
+
+    fn recv_raw(&mut self, timeout: Duration) -> Result<Vec<u8>> {
+        let mut buf = [0u8; 64];
+        let nread = self.bulk_transfer(ENDPOINT_IN, &mut buf, timeout)?;
+        Ok(buf[..nread].to_vec())
+    }
+}